@@ -11,22 +11,226 @@ use habitat_common::ui::{Status,
 use habitat_core::package::PackageIdent;
 use handlebars::Handlebars;
 use serde_json;
-use std::{fs,
+use std::{env,
+          fmt,
+          fs,
           path::{Path,
                  PathBuf},
+          process::{Command,
+                    Stdio},
           str::FromStr};
 
 // This code makes heavy use of `#[cfg(unix)]` and `#[cfg(windows)]`. This should potentially be
 // changed to use the various target feature flags.
 
-/// The `Dockerfile` template.
-#[cfg(unix)]
-const DOCKERFILE: &str = include_str!("../defaults/Dockerfile.hbs");
-#[cfg(windows)]
-const DOCKERFILE: &str = include_str!("../defaults/Dockerfile_win.hbs");
 /// The build report template.
 const BUILD_REPORT: &str = include_str!("../defaults/last_docker_export.env.hbs");
 
+/// A single Dockerfile instruction.
+#[derive(Clone, Debug)]
+enum Instruction {
+    From(String),
+    Env(String, String),
+    Expose(Vec<String>),
+    Copy(String, String),
+    Label(String, String),
+    EntryPoint(Vec<String>),
+    Cmd(Vec<String>),
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Instruction::From(image) => write!(f, "FROM {}", image),
+            Instruction::Env(key, value) => write!(f, "ENV {}={}", key, value),
+            Instruction::Expose(ports) => write!(f, "EXPOSE {}", ports.join(" ")),
+            Instruction::Copy(src, dst) => write!(f, "COPY {} {}", src, dst),
+            Instruction::Label(key, value) => write!(f, "LABEL {}=\"{}\"", key, value),
+            Instruction::EntryPoint(argv) => write!(f, "ENTRYPOINT [{}]", quoted_argv(argv)),
+            Instruction::Cmd(argv) => write!(f, "CMD [{}]", quoted_argv(argv)),
+        }
+    }
+}
+
+fn quoted_argv(argv: &[String]) -> String {
+    argv.iter()
+        .map(|arg| format!("{:?}", arg))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// A Dockerfile, built programmatically from a `BuildRoot` context rather than
+/// rendered from a string template. This makes the generated instructions testable
+/// without invoking Docker.
+///
+/// This type and its builder methods are private to this module: the only caller is
+/// `dockerfile_instructions` below. A user who wants instructions this AST doesn't
+/// produce (e.g. extra `ARG`s) still needs `DockerBuildRoot::from_build_root`'s
+/// `dockerfile_template` override, which bypasses this type entirely.
+#[derive(Clone, Debug, Default)]
+struct Dockerfile {
+    instructions: Vec<Instruction>,
+}
+
+impl Dockerfile {
+    fn new() -> Self { Dockerfile { instructions: Vec::new() } }
+
+    fn from(&mut self, image: &str) -> &mut Self {
+        self.instructions.push(Instruction::From(image.to_string()));
+        self
+    }
+
+    fn env(&mut self, key: &str, value: &str) -> &mut Self {
+        self.instructions
+            .push(Instruction::Env(key.to_string(), value.to_string()));
+        self
+    }
+
+    fn expose(&mut self, ports: &[String]) -> &mut Self {
+        if !ports.is_empty() {
+            self.instructions.push(Instruction::Expose(ports.to_vec()));
+        }
+        self
+    }
+
+    fn copy(&mut self, src: &str, dst: &str) -> &mut Self {
+        self.instructions
+            .push(Instruction::Copy(src.to_string(), dst.to_string()));
+        self
+    }
+
+    fn label(&mut self, key: &str, value: &str) -> &mut Self {
+        self.instructions
+            .push(Instruction::Label(key.to_string(), value.to_string()));
+        self
+    }
+
+    fn entrypoint(&mut self, argv: &[String]) -> &mut Self {
+        self.instructions.push(Instruction::EntryPoint(argv.to_vec()));
+        self
+    }
+
+    fn cmd(&mut self, argv: &[String]) -> &mut Self {
+        self.instructions.push(Instruction::Cmd(argv.to_vec()));
+        self
+    }
+}
+
+impl fmt::Display for Dockerfile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, instruction) in self.instructions.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", instruction)?;
+        }
+        Ok(())
+    }
+}
+
+/// Builds the Dockerfile instructions for a Unix-based image.
+#[cfg(unix)]
+#[allow(clippy::too_many_arguments)]
+fn dockerfile_instructions(base_image: &str,
+                           rootfs: &str,
+                           path: &str,
+                           hab_path: &str,
+                           exposes: &[String],
+                           multi_layer: bool,
+                           primary_svc_ident: &str,
+                           installed_primary_svc_ident: &str,
+                           environment: &[(String, String)],
+                           packages: &[String])
+                           -> Dockerfile {
+    let mut file = Dockerfile::new();
+    file.from(base_image);
+    if multi_layer {
+        for package in packages {
+            file.copy(&format!("{}/hab/pkgs/{}", rootfs, package),
+                      &format!("/hab/pkgs/{}", package));
+        }
+    } else {
+        file.copy(rootfs, "/");
+    }
+    file.env("PATH", path);
+    for (key, value) in environment {
+        file.env(key, value);
+    }
+    file.label("HABITAT_PRIMARY_SERVICE", primary_svc_ident)
+        .label("HABITAT_INSTALLED_PRIMARY_SERVICE", installed_primary_svc_ident)
+        .expose(exposes)
+        .entrypoint(&["/init.sh".to_string()])
+        .cmd(&[hab_path.to_string(), "sup".to_string(), "run".to_string()]);
+    file
+}
+
+/// Builds the Dockerfile instructions for a Windows-based image.
+#[cfg(windows)]
+#[allow(clippy::too_many_arguments)]
+fn dockerfile_instructions(base_image: &str,
+                           rootfs: &str,
+                           path: &str,
+                           hab_path: &str,
+                           exposes: &[String],
+                           multi_layer: bool,
+                           primary_svc_ident: &str,
+                           installed_primary_svc_ident: &str,
+                           environment: &[(String, String)],
+                           packages: &[String])
+                           -> Dockerfile {
+    let mut file = Dockerfile::new();
+    file.from(base_image);
+    if multi_layer {
+        for package in packages {
+            file.copy(&format!("{}\\hab\\pkgs\\{}", rootfs, package),
+                      &format!("C:\\hab\\pkgs\\{}", package));
+        }
+    } else {
+        file.copy(rootfs, "C:\\");
+    }
+    file.env("PATH", path);
+    for (key, value) in environment {
+        file.env(key, value);
+    }
+    file.label("HABITAT_PRIMARY_SERVICE", primary_svc_ident)
+        .label("HABITAT_INSTALLED_PRIMARY_SERVICE", installed_primary_svc_ident)
+        .expose(exposes)
+        .entrypoint(&[hab_path.to_string(), "sup".to_string(), "run".to_string()]);
+    file
+}
+
+/// Builds the Handlebars render context for a user-supplied custom Dockerfile
+/// template, preserving the context shape a template was originally written against
+/// (chunk0-3): `exposes` is a single space-joined string and `environment` is rendered
+/// as given (typically a `{key: value}` map, so `{{#each environment}}{{@key}}={{this}}
+/// {{/each}}`-style templates keep working) rather than the list-of-pairs shape used
+/// internally by `dockerfile_instructions`.
+#[allow(clippy::too_many_arguments)]
+fn custom_dockerfile_context(base_image: &str,
+                             rootfs: &str,
+                             path: &str,
+                             hab_path: &str,
+                             exposes: &[String],
+                             multi_layer: bool,
+                             primary_svc_ident: &str,
+                             installed_primary_svc_ident: &str,
+                             environment: serde_json::Value,
+                             packages: &[String])
+                             -> serde_json::Value {
+    json!({
+        "base_image": base_image,
+        "rootfs": rootfs,
+        "path": path,
+        "hab_path": hab_path,
+        "exposes": exposes.join(" "),
+        "multi_layer": multi_layer,
+        "primary_svc_ident": primary_svc_ident,
+        "installed_primary_svc_ident": installed_primary_svc_ident,
+        "environment": environment,
+        "packages": packages,
+    })
+}
+
 // TODO (CM): public temporarily
 pub(crate) trait Identified {
     /// The base name of an image.
@@ -71,16 +275,282 @@ pub(crate) trait Identified {
     }
 }
 
+/// The environment variable consulted to force a particular container engine, bypassing
+/// auto-detection.
+const ENGINE_ENVVAR: &str = "HAB_CONTAINER_ENGINE";
+
+/// The container engine backends that this exporter knows how to drive.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EngineKind {
+    Docker,
+    Podman,
+    Buildah,
+}
+
+impl fmt::Display for EngineKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match *self {
+            EngineKind::Docker => "docker",
+            EngineKind::Podman => "podman",
+            EngineKind::Buildah => "buildah",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// A container engine resolved to a concrete binary on this host.
+///
+/// Every engine invocation in this module goes through an `Engine` rather than
+/// hard-coding the `docker` binary, so the exporter can also target Podman or Buildah
+/// hosts (for example, a rootless Podman host with no Docker daemon at all).
+#[derive(Clone, Debug)]
+pub struct Engine {
+    kind:   EngineKind,
+    binary: PathBuf,
+}
+
+impl Engine {
+    /// Detects the container engine to use.
+    ///
+    /// `requested` is typically the value of a `--engine` CLI flag. If not given, the
+    /// `HAB_CONTAINER_ENGINE` environment variable is consulted. If neither specifies an
+    /// engine, `docker` is tried first, falling back to `podman`.
+    ///
+    /// # Errors
+    ///
+    /// * If an explicitly requested engine is not recognized
+    /// * If the resolved engine's binary cannot be found on `PATH`
+    /// * If no engine could be found at all
+    pub fn detect(requested: Option<&str>) -> Result<Self> {
+        let requested = requested.map(ToString::to_string)
+                                  .or_else(|| env::var(ENGINE_ENVVAR).ok());
+
+        match requested {
+            Some(name) => {
+                let kind = match name.to_lowercase().as_str() {
+                    "docker" => EngineKind::Docker,
+                    "podman" => EngineKind::Podman,
+                    "buildah" => EngineKind::Buildah,
+                    other => return Err(Error::UnknownEngine(other.to_string())),
+                };
+                Self::resolve(kind)
+            }
+            None => {
+                Self::resolve(EngineKind::Docker).or_else(|_| Self::resolve(EngineKind::Podman))
+            }
+        }
+    }
+
+    fn resolve(kind: EngineKind) -> Result<Self> {
+        let binary = kind.to_string();
+        find_on_path(&binary).map(|binary| Engine { kind, binary })
+                             .ok_or_else(|| Error::EngineBinaryNotFound(binary))
+    }
+
+    /// The kind of engine this is (`Docker`, `Podman`, or `Buildah`).
+    pub fn kind(&self) -> EngineKind { self.kind }
+
+    /// Starts a `Command` invoking this engine's resolved binary.
+    pub fn command(&self) -> Command { Command::new(&self.binary) }
+
+    /// Whether this engine is targeting a remote host (a `DOCKER_HOST`/`CONTAINER_HOST`
+    /// pointing at a TCP or SSH endpoint), in which case the build root's rootfs is not
+    /// on the same filesystem as the engine and must be staged via a data volume.
+    pub fn is_remote(&self) -> bool {
+        let envvar = match self.kind {
+            EngineKind::Docker => "DOCKER_HOST",
+            // Podman (and Buildah, which shares Podman's libpod plumbing) consults
+            // `CONTAINER_HOST` for a remote service, not `DOCKER_HOST`.
+            EngineKind::Podman | EngineKind::Buildah => "CONTAINER_HOST",
+        };
+        env::var(envvar).map(|host| host.starts_with("tcp://") || host.starts_with("ssh://"))
+                         .unwrap_or(false)
+    }
+}
+
+/// Searches `PATH` for an executable named `binary`, mirroring what the shell would find.
+fn find_on_path(binary: &str) -> Option<PathBuf> {
+    env::var_os("PATH").and_then(|paths| {
+                           env::split_paths(&paths).map(|dir| dir.join(binary))
+                                                    .find(|candidate| candidate.is_file())
+                       })
+}
+
+/// The name of the persistent data volume reused across exports for a given package
+/// ident, so repeated exports against a remote engine don't recopy the rootfs.
+fn persistent_volume_name(ident: &str) -> String {
+    format!("hab-export-{}", ident.replace('/', "-"))
+}
+
+/// The name of a one-off data volume used for a single remote export.
+fn scratch_volume_name() -> String { format!("hab-export-scratch-{}", std::process::id()) }
+
+/// The image used to host the helper containers that stage a build context into (and
+/// back out of) a remote data volume.
+const HELPER_IMAGE: &str = "busybox";
+
+/// The auth file name a given engine expects to find registry credentials in.
+fn auth_file_name(kind: EngineKind) -> &'static str {
+    match kind {
+        EngineKind::Docker => "config.json",
+        EngineKind::Podman | EngineKind::Buildah => "auth.json",
+    }
+}
+
+/// The Docker label stamped on every image this exporter builds, identifying the
+/// Habitat package ident it was built from.
+const IDENT_LABEL: &str = "hab.package.ident";
+/// The Docker label stamped on every image this exporter builds, identifying the
+/// channel it was exported from.
+const CHANNEL_LABEL: &str = "hab.channel";
+
+/// A named data volume on a container engine's (possibly remote) host, used to stage a
+/// build context when the engine is not reachable on the local filesystem (e.g. an
+/// engine reached via `DOCKER_HOST` over TCP or SSH).
+///
+/// Scratch volumes are removed when this handle is dropped, mirroring how
+/// `DockerBuildRoot::destroy` cleans up its temporary workdir; a volume marked
+/// persistent (keyed by package ident) is left in place so subsequent exports can reuse
+/// it without recopying the rootfs.
+pub struct DataVolume {
+    engine:     Engine,
+    name:       String,
+    persistent: bool,
+    removed:    bool,
+}
+
+impl DataVolume {
+    /// The volume's name on the engine.
+    pub fn name(&self) -> &str { &self.name }
+
+    /// Whether a volume named `name` already exists on the engine's host.
+    pub fn exists(engine: &Engine, name: &str) -> Result<bool> {
+        let mut cmd = engine.command();
+        cmd.arg("volume")
+           .arg("inspect")
+           .arg(name)
+           .stdout(Stdio::null())
+           .stderr(Stdio::null());
+        debug!("Running: {:?}", &cmd);
+        Ok(cmd.spawn()?.wait()?.success())
+    }
+
+    /// Creates a named volume on the engine's host, or does nothing if one by that
+    /// name already exists (`docker volume create`/`podman volume create` are
+    /// idempotent).
+    pub fn create(engine: &Engine, name: &str, persistent: bool) -> Result<Self> {
+        let mut cmd = engine.command();
+        cmd.arg("volume").arg("create").arg(name);
+        debug!("Running: {:?}", &cmd);
+        let exit_status = cmd.spawn()?.wait()?;
+        if !exit_status.success() {
+            return Err(Error::CreateVolumeFailed(name.to_string(), exit_status));
+        }
+        Ok(DataVolume { engine: engine.clone(),
+                         name: name.to_string(),
+                         persistent,
+                         removed: false })
+    }
+
+    /// Streams the contents of `workdir` into this volume via a short-lived helper
+    /// container.
+    fn populate(&self, workdir: &Path) -> Result<()> {
+        let mut tar = Command::new("tar");
+        tar.arg("-C").arg(workdir).arg("-c").arg(".").stdout(Stdio::piped());
+        debug!("Running: {:?}", &tar);
+        let mut tar_child = tar.spawn()?;
+        let tar_stdout = tar_child.stdout.take().expect("tar stdout was piped");
+
+        let mut cmd = self.engine.command();
+        cmd.arg("run")
+           .arg("--rm")
+           .arg("-i")
+           .arg("-v")
+           .arg(format!("{}:/data", self.name))
+           .arg(HELPER_IMAGE)
+           .arg("tar")
+           .arg("-C")
+           .arg("/data")
+           .arg("-x")
+           .stdin(tar_stdout);
+        debug!("Running: {:?}", &cmd);
+        let exit_status = cmd.spawn()?.wait()?;
+        tar_child.wait()?;
+        if !exit_status.success() {
+            return Err(Error::PopulateVolumeFailed(self.name.clone(), exit_status));
+        }
+        Ok(())
+    }
+
+    /// Removes this volume from the engine immediately, regardless of whether it is
+    /// marked persistent.
+    ///
+    /// Calling this explicitly is only required to force removal of a volume marked
+    /// persistent; scratch volumes are removed automatically when dropped.
+    pub fn destroy(mut self) -> Result<()> {
+        self.remove()?;
+        self.removed = true;
+        Ok(())
+    }
+
+    /// Removes this volume from the engine immediately, without consuming the handle.
+    pub fn remove(&self) -> Result<()> {
+        let mut cmd = self.engine.command();
+        cmd.arg("volume").arg("rm").arg("-f").arg(&self.name);
+        debug!("Running: {:?}", &cmd);
+        let exit_status = cmd.spawn()?.wait()?;
+        if !exit_status.success() {
+            return Err(Error::RemoveVolumeFailed(self.name.clone(), exit_status));
+        }
+        Ok(())
+    }
+}
+
+impl Drop for DataVolume {
+    fn drop(&mut self) {
+        if self.removed || self.persistent {
+            return;
+        }
+        if let Err(e) = self.remove() {
+            warn!("Failed to remove scratch data volume '{}': {}", self.name, e);
+        }
+    }
+}
+
 /// A builder used to create a Docker image.
 pub struct ImageBuilder {
+    /// The container engine used to run the build.
+    engine:            Engine,
     /// The base workdir which hosts the root file system.
-    workdir: PathBuf,
+    workdir:           PathBuf,
     /// The name for the image.
-    name:    String,
+    name:              String,
     /// A list of tags for the image.
-    tags:    Vec<String>,
+    tags:              Vec<String>,
     /// Optional memory limit to pass to pass to the docker build
-    memory:  Option<String>,
+    memory:            Option<String>,
+    /// When building against a remote engine, a key (typically the package ident)
+    /// identifying a persistent data volume to reuse across exports, rather than
+    /// staging and discarding a scratch volume.
+    persistent_volume: Option<String>,
+    /// `--build-arg KEY=VALUE` pairs to forward to the build invocation.
+    build_args:        Vec<(String, String)>,
+    /// Shell commands run in the build root, in order, before the image is built.
+    pre_build:         Vec<String>,
+    /// Target platforms (e.g. `linux/arm64`) for a multi-architecture `docker buildx
+    /// build`. When non-empty, the build switches from `docker build` to `buildx
+    /// build --platform` and pushes directly via `--push`.
+    platforms:         Vec<String>,
+    /// Registry credentials used to authenticate the implicit push a multi-arch build
+    /// performs, since that push happens before a `DockerImage` exists to call
+    /// `DockerImage::push` on.
+    registry_auth:     Option<(Credentials, Option<String>)>,
+    /// The package ident this image is built from, stamped onto the image as the
+    /// `hab.package.ident` label so `ImageManager` can find it later.
+    ident:             String,
+    /// The channel this image was exported from, stamped onto the image as the
+    /// `hab.channel` label.
+    channel:           String,
 }
 
 impl Identified for ImageBuilder {
@@ -90,11 +560,26 @@ impl Identified for ImageBuilder {
 }
 
 impl ImageBuilder {
-    fn new(workdir: &Path, name: &str) -> Self {
-        ImageBuilder { workdir: workdir.to_path_buf(),
+    fn new(engine: Engine, workdir: &Path, name: &str, ident: &str, channel: &str) -> Self {
+        ImageBuilder { engine,
+                       workdir: workdir.to_path_buf(),
                        name:    name.to_string(),
                        tags:    Vec::new(),
-                       memory:  None, }
+                       memory:  None,
+                       persistent_volume: None,
+                       build_args: Vec::new(),
+                       pre_build: Vec::new(),
+                       platforms: Vec::new(),
+                       registry_auth: None,
+                       ident:   ident.to_string(),
+                       channel: channel.to_string(), }
+    }
+
+    /// The `--label` arguments stamping this image with its package ident and
+    /// channel, so `ImageManager` can enumerate and clean it up later.
+    fn label_args(&self) -> Vec<String> {
+        vec![format!("{}={}", IDENT_LABEL, self.ident),
+             format!("{}={}", CHANNEL_LABEL, self.channel)]
     }
 
     /// Adds a tag for the Docker image.
@@ -109,19 +594,97 @@ impl ImageBuilder {
         self
     }
 
+    /// Reuses a persistent, engine-side data volume keyed by `key` (typically the
+    /// package ident) across exports against a remote engine, instead of staging a
+    /// scratch volume that is discarded once the build completes. If the volume
+    /// already exists from a prior export, its contents are reused as-is instead of
+    /// being re-copied from the local workdir.
+    pub fn persistent_volume(mut self, key: &str) -> Self {
+        self.persistent_volume = Some(key.to_string());
+        self
+    }
+
+    /// Adds a `--build-arg KEY=VALUE` to the build invocation.
+    pub fn build_arg(mut self, key: &str, value: &str) -> Self {
+        self.build_args.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    /// Adds a shell command to run in the build root, in order, before the image is
+    /// built (useful for injecting credentials, installing extra OS packages, or
+    /// editing the rootfs).
+    pub fn pre_build(mut self, command: &str) -> Self {
+        self.pre_build.push(command.to_string());
+        self
+    }
+
+    /// Adds a target platform (e.g. `linux/arm64`) to build for via `docker buildx`.
+    ///
+    /// When any platforms are set, the build emits a multi-platform manifest list
+    /// instead of a single-arch image, useful for producing images for architectures
+    /// the build host cannot run (e.g. ARM images built on an x86 host for packages
+    /// that were themselves cross-built).
+    pub fn platform(mut self, platform: &str) -> Self {
+        self.platforms.push(platform.to_string());
+        self
+    }
+
+    /// Supplies registry credentials to authenticate the implicit push performed by a
+    /// multi-arch build (see `build_multi_arch`). Has no effect unless `platform` is
+    /// also used.
+    pub fn registry_auth(mut self, credentials: Credentials, registry_url: Option<String>) -> Self {
+        self.registry_auth = Some((credentials, registry_url));
+        self
+    }
+
+    /// Runs the configured pre-build commands, in order, in the build root.
+    ///
+    /// # Errors
+    ///
+    /// * If a pre-build command cannot be spawned or exits non-zero
+    fn run_pre_build(&self) -> Result<()> {
+        for command in &self.pre_build {
+            debug!("Running pre-build command: {:?}", command);
+            let exit_status = Command::new("sh").arg("-c")
+                                                 .arg(command)
+                                                 .current_dir(&self.workdir)
+                                                 .spawn()?
+                                                 .wait()?;
+            if !exit_status.success() {
+                return Err(Error::PreBuildCommandFailed(command.clone(), exit_status));
+            }
+        }
+        Ok(())
+    }
+
     /// Builds the Docker image locally and returns the corresponding `DockerImage`.
     ///
     /// # Errors
     ///
+    /// * If a pre-build command fails
     /// * If building the Docker image fails
     pub fn build(self) -> Result<DockerImage> {
-        let mut cmd = util::docker_cmd();
+        self.run_pre_build()?;
+        if !self.platforms.is_empty() {
+            return self.build_multi_arch();
+        }
+        if self.engine.is_remote() {
+            return self.build_remote();
+        }
+
+        let mut cmd = self.engine.command();
         cmd.current_dir(&self.workdir)
            .arg("build")
            .arg("--force-rm");
         if let Some(ref mem) = self.memory {
             cmd.arg("--memory").arg(mem);
         }
+        for (key, value) in &self.build_args {
+            cmd.arg("--build-arg").arg(format!("{}={}", key, value));
+        }
+        for label in &self.label_args() {
+            cmd.arg("--label").arg(label);
+        }
         for identifier in &self.expanded_identifiers() {
             cmd.arg("--tag").arg(identifier);
         }
@@ -137,14 +700,171 @@ impl ImageBuilder {
             None => self.image_id(&self.name)?,
         };
 
-        Ok(DockerImage { id,
+        Ok(DockerImage { engine: self.engine.clone(),
+                         id,
+                         name: self.name,
+                         tags: self.tags,
+                         workdir: self.workdir.to_owned() })
+    }
+
+    /// Builds the Docker image against a remote engine, whose rootfs is not on the
+    /// local filesystem.
+    ///
+    /// The workdir is staged into a named data volume on the engine's host (a
+    /// persistent one if `persistent_volume` was set, otherwise a scratch volume that
+    /// is removed once this builder is dropped), then streamed back out as a build
+    /// context. A persistent volume left over from an earlier export is reused without
+    /// restaging the workdir into it.
+    ///
+    /// # Errors
+    ///
+    /// * If the data volume cannot be created or populated
+    /// * If building the Docker image fails
+    fn build_remote(self) -> Result<DockerImage> {
+        let (volume, already_populated) = match &self.persistent_volume {
+            Some(key) => {
+                let name = persistent_volume_name(key);
+                let pre_existing = DataVolume::exists(&self.engine, &name)?;
+                (DataVolume::create(&self.engine, &name, true)?, pre_existing)
+            }
+            None => (DataVolume::create(&self.engine, &scratch_volume_name(), false)?, false),
+        };
+        // A persistent volume that already existed was already populated by an earlier
+        // export; re-copying the workdir into it would defeat the point of reusing it.
+        if !already_populated {
+            volume.populate(&self.workdir)?;
+        }
+
+        let mut tar_cmd = self.engine.command();
+        tar_cmd.arg("run")
+               .arg("--rm")
+               .arg("-v")
+               .arg(format!("{}:/context:ro", volume.name))
+               .arg(HELPER_IMAGE)
+               .arg("tar")
+               .arg("-C")
+               .arg("/context")
+               .arg("-c")
+               .arg(".")
+               .stdout(Stdio::piped());
+        debug!("Running: {:?}", &tar_cmd);
+        let mut tar_child = tar_cmd.spawn()?;
+        let tar_stdout = tar_child.stdout.take().expect("tar stdout was piped");
+
+        let mut cmd = self.engine.command();
+        cmd.arg("build").arg("--force-rm");
+        if let Some(ref mem) = self.memory {
+            cmd.arg("--memory").arg(mem);
+        }
+        for (key, value) in &self.build_args {
+            cmd.arg("--build-arg").arg(format!("{}={}", key, value));
+        }
+        for label in &self.label_args() {
+            cmd.arg("--label").arg(label);
+        }
+        for identifier in &self.expanded_identifiers() {
+            cmd.arg("--tag").arg(identifier);
+        }
+        cmd.arg("-").stdin(tar_stdout);
+        debug!("Running: {:?}", &cmd);
+        let exit_status = cmd.spawn()?.wait()?;
+        tar_child.wait()?;
+        if !exit_status.success() {
+            return Err(Error::BuildFailed(exit_status).into());
+        }
+
+        let id = match self.tags.first() {
+            Some(tag) => self.image_id(&format!("{}:{}", &self.name, tag))?,
+            None => self.image_id(&self.name)?,
+        };
+
+        Ok(DockerImage { engine: self.engine.clone(),
+                         id,
+                         name: self.name,
+                         tags: self.tags,
+                         workdir: self.workdir.to_owned() })
+    }
+
+    /// Writes registry credentials to the auth file the configured engine expects to
+    /// find in the build root, mirroring `DockerImage::create_docker_config_file`.
+    fn write_registry_auth(&self, credentials: &Credentials, registry_url: Option<&str>) -> Result<()> {
+        let config = self.workdir.join(auth_file_name(self.engine.kind()));
+        fs::create_dir_all(&self.workdir)?;
+        let registry = registry_url.unwrap_or("https://index.docker.io/v1/");
+        debug!("Using registry: {:?}", registry);
+        let json = json!({ "auths": { registry: { "auth": credentials.token } } });
+        util::write_file(&config, &serde_json::to_string(&json).unwrap())?;
+        Ok(())
+    }
+
+    /// Builds (and pushes) a multi-architecture image via `docker buildx build
+    /// --platform`.
+    ///
+    /// `buildx` is a Docker CLI plugin with no Podman/Buildah equivalent, so this is
+    /// only supported against a `Docker` engine.
+    ///
+    /// Buildx cannot load a multi-platform manifest list into the local engine, so the
+    /// image is pushed directly as part of the build rather than in a separate
+    /// `DockerImage::push` step; the resulting `DockerImage` has no local image ID and
+    /// should not be passed to `DockerImage::rm`. If `registry_auth` was set, its
+    /// credentials are written to a `config.json` in the build root, which is passed to
+    /// the engine via `--config`; otherwise the push relies on credentials already
+    /// configured for the engine out-of-band.
+    ///
+    /// # Errors
+    ///
+    /// * If the engine is not `Docker`
+    /// * If `docker buildx` is not available
+    /// * If writing the registry auth file fails
+    /// * If the build (and push) fails
+    fn build_multi_arch(self) -> Result<DockerImage> {
+        if self.engine.kind() != EngineKind::Docker {
+            return Err(Error::MultiArchUnsupportedEngine(self.engine.kind()));
+        }
+
+        if let Some((credentials, registry_url)) = &self.registry_auth {
+            self.write_registry_auth(credentials, registry_url.as_deref())?;
+        }
+
+        let mut cmd = self.engine.command();
+        if self.registry_auth.is_some() {
+            cmd.arg("--config").arg(self.workdir.to_str().unwrap());
+        }
+        cmd.current_dir(&self.workdir)
+           .arg("buildx")
+           .arg("build")
+           .arg("--platform")
+           .arg(self.platforms.join(","))
+           .arg("--push")
+           .arg("--force-rm");
+        if let Some(ref mem) = self.memory {
+            cmd.arg("--memory").arg(mem);
+        }
+        for (key, value) in &self.build_args {
+            cmd.arg("--build-arg").arg(format!("{}={}", key, value));
+        }
+        for label in &self.label_args() {
+            cmd.arg("--label").arg(label);
+        }
+        for identifier in &self.expanded_identifiers() {
+            cmd.arg("--tag").arg(identifier);
+        }
+        cmd.arg(".");
+        debug!("Running: {:?}", &cmd);
+        let exit_status = cmd.spawn()?.wait()?;
+        if !exit_status.success() {
+            return Err(Error::BuildFailed(exit_status).into());
+        }
+
+        Ok(DockerImage { engine: self.engine.clone(),
+                         id: String::new(),
                          name: self.name,
                          tags: self.tags,
                          workdir: self.workdir.to_owned() })
     }
 
     fn image_id(&self, image_tag: &str) -> Result<String> {
-        let mut cmd = util::docker_cmd();
+        let mut cmd = self.engine.command();
         cmd.arg("images").arg("-q").arg(image_tag);
         debug!("Running: {:?}", &cmd);
         let output = cmd.output()?;
@@ -159,6 +879,8 @@ impl ImageBuilder {
 
 /// A built Docker image which exists locally.
 pub struct DockerImage {
+    /// The container engine this image was built with.
+    engine:  Engine,
     /// The image ID for this image.
     id:      String,
     /// The name of this image.
@@ -197,10 +919,19 @@ impl DockerImage {
         for image_tag in self.expanded_identifiers() {
             ui.status(Status::Uploading,
                       format!("image '{}' to remote registry", image_tag))?;
-            let mut cmd = util::docker_cmd();
-            cmd.arg("--config");
-            cmd.arg(self.workdir.to_str().unwrap());
-            cmd.arg("push").arg(&image_tag);
+            let mut cmd = self.engine.command();
+            match self.engine.kind() {
+                // Podman and Buildah take the auth file as a flag on the `push`
+                // subcommand itself, rather than as a global `--config` flag.
+                EngineKind::Podman | EngineKind::Buildah => {
+                    cmd.arg("push").arg("--authfile").arg(self.auth_file_path());
+                }
+                EngineKind::Docker => {
+                    cmd.arg("--config").arg(self.workdir.to_str().unwrap());
+                    cmd.arg("push");
+                }
+            }
+            cmd.arg(&image_tag);
             debug!("Running: {:?}", &cmd);
             let exit_status = cmd.spawn()?.wait()?;
             if !exit_status.success() {
@@ -227,7 +958,7 @@ impl DockerImage {
 
         for image_tag in self.expanded_identifiers() {
             ui.status(Status::Deleting, format!("local image '{}'", image_tag))?;
-            let mut cmd = util::docker_cmd();
+            let mut cmd = self.engine.command();
             cmd.arg("rmi").arg(image_tag);
             debug!("Running: {:?}", &cmd);
             let exit_status = cmd.spawn()?.wait()?;
@@ -269,11 +1000,15 @@ impl DockerImage {
         Ok(())
     }
 
+    /// The path to the auth file consulted when pushing, named according to what the
+    /// configured engine expects to find in the build root.
+    fn auth_file_path(&self) -> PathBuf { self.workdir.join(auth_file_name(self.engine.kind())) }
+
     pub fn create_docker_config_file(&self,
                                      credentials: &Credentials,
                                      registry_url: Option<&str>)
                                      -> Result<()> {
-        let config = self.workdir.join("config.json");
+        let config = self.auth_file_path();
         fs::create_dir_all(&self.workdir)?;
         let registry = match registry_url {
             Some(url) => url,
@@ -293,18 +1028,30 @@ impl DockerImage {
 }
 
 /// A temporary file system build root for building a Docker image, based on Habitat packages.
-pub struct DockerBuildRoot(BuildRoot);
+pub struct DockerBuildRoot {
+    build_root:          BuildRoot,
+    /// A user-provided Dockerfile template, rendered through Handlebars and used in
+    /// place of the programmatically generated default.
+    dockerfile_template: Option<PathBuf>,
+}
 
 impl DockerBuildRoot {
     /// Builds a completed Docker build root from a `BuildRoot`, performing any final tasks on the
     /// root file system.
     ///
+    /// `dockerfile_template` optionally overrides the generated `Dockerfile` with a
+    /// user-provided Handlebars template, rendered against the build root's context.
+    ///
     /// # Errors
     ///
     /// * If any remaining tasks cannot be performed in the build root
     #[cfg(unix)]
-    pub fn from_build_root(build_root: BuildRoot, ui: &mut UI) -> Result<Self> {
-        let root = DockerBuildRoot(build_root);
+    pub fn from_build_root(build_root: BuildRoot,
+                           dockerfile_template: Option<PathBuf>,
+                           ui: &mut UI)
+                           -> Result<Self> {
+        let root = DockerBuildRoot { build_root,
+                                     dockerfile_template };
         root.add_users_and_groups(ui)?;
         root.create_entrypoint(ui)?;
         root.create_dockerfile(ui)?;
@@ -313,8 +1060,12 @@ impl DockerBuildRoot {
     }
 
     #[cfg(windows)]
-    pub fn from_build_root(build_root: BuildRoot, ui: &mut UI) -> Result<Self> {
-        let root = DockerBuildRoot(build_root);
+    pub fn from_build_root(build_root: BuildRoot,
+                           dockerfile_template: Option<PathBuf>,
+                           ui: &mut UI)
+                           -> Result<Self> {
+        let root = DockerBuildRoot { build_root,
+                                     dockerfile_template };
         root.create_dockerfile(ui)?;
 
         Ok(root)
@@ -329,14 +1080,14 @@ impl DockerBuildRoot {
     /// # Errors
     ///
     /// * If the temporary work directory cannot be removed
-    pub fn destroy(self, ui: &mut UI) -> Result<()> { self.0.destroy(ui) }
+    pub fn destroy(self, ui: &mut UI) -> Result<()> { self.build_root.destroy(ui) }
 
     #[cfg(unix)]
     fn add_users_and_groups(&self, ui: &mut UI) -> Result<()> {
         use std::{fs::OpenOptions,
                   io::Write};
 
-        let ctx = self.0.ctx();
+        let ctx = self.build_root.ctx();
         let (users, groups) = ctx.svc_users_and_groups()?;
         {
             let file = "etc/passwd";
@@ -369,7 +1120,7 @@ impl DockerBuildRoot {
         const INIT_SH: &str = include_str!("../defaults/init.sh.hbs");
 
         ui.status(Status::Creating, "entrypoint script")?;
-        let ctx = self.0.ctx();
+        let ctx = self.build_root.ctx();
         let busybox_shell =
             util::pkg_path_for(&util::busybox_ident()?, ctx.rootfs())?.join("bin/sh");
         let json = json!({
@@ -388,52 +1139,344 @@ impl DockerBuildRoot {
 
     fn create_dockerfile(&self, ui: &mut UI) -> Result<()> {
         ui.status(Status::Creating, "image Dockerfile")?;
-        let ctx = self.0.ctx();
-        let json = json!({
-            "base_image": ctx.base_image(),
-            "rootfs": ctx.rootfs().file_name().expect("file_name exists")
-                .to_string_lossy()
-                .as_ref(),
-            "path": ctx.env_path(),
-            "hab_path": util::pkg_path_for(
-                &PackageIdent::from_str("core/hab")?,
-                ctx.rootfs())?.join("bin/hab")
-                .to_string_lossy()
-                .replace("\\", "/"),
-            "exposes": ctx.svc_exposes().join(" "),
-            "multi_layer": ctx.multi_layer(),
-            "primary_svc_ident": ctx.primary_svc_ident().to_string(),
-            "installed_primary_svc_ident": ctx.installed_primary_svc_ident()?.to_string(),
-            "environment": ctx.environment,
-            "packages": self.0.graph().reverse_topological_sort().iter().map(ToString::to_string).collect::<Vec<_>>(),
-        });
-        util::write_file(self.0.workdir().join("Dockerfile"),
-                         &Handlebars::new().template_render(DOCKERFILE, &json)
-                                           .map_err(SyncFailure::new)?)?;
+
+        let ctx = self.build_root.ctx();
+        let rootfs = ctx.rootfs()
+                        .file_name()
+                        .expect("file_name exists")
+                        .to_string_lossy()
+                        .into_owned();
+        let hab_path = util::pkg_path_for(&PackageIdent::from_str("core/hab")?, ctx.rootfs())?.join("bin/hab")
+                                                                                                .to_string_lossy()
+                                                                                                .replace("\\", "/");
+        let packages = self.build_root
+                           .graph()
+                           .reverse_topological_sort()
+                           .iter()
+                           .map(ToString::to_string)
+                           .collect::<Vec<_>>();
+        let environment = ctx.environment
+                             .iter()
+                             .map(|(k, v)| (k.to_string(), v.to_string()))
+                             .collect::<Vec<_>>();
+
+        let contents = match &self.dockerfile_template {
+            // A user-provided Dockerfile is still rendered through Handlebars with the
+            // same context the programmatic Dockerfile below is built from, so
+            // `{{base_image}}`-style placeholders keep working.
+            Some(path) => {
+                let template = fs::read_to_string(path)?;
+                let json = custom_dockerfile_context(&ctx.base_image(),
+                                                     &rootfs,
+                                                     &ctx.env_path(),
+                                                     &hab_path,
+                                                     &ctx.svc_exposes(),
+                                                     ctx.multi_layer(),
+                                                     &ctx.primary_svc_ident().to_string(),
+                                                     &ctx.installed_primary_svc_ident()?
+                                                         .to_string(),
+                                                     serde_json::to_value(&ctx.environment)
+                                                     .expect("environment map serializes"),
+                                                     &packages);
+                Handlebars::new().template_render(&template, &json)
+                                 .map_err(SyncFailure::new)?
+            }
+            None => dockerfile_instructions(&ctx.base_image(),
+                                            &rootfs,
+                                            &ctx.env_path(),
+                                            &hab_path,
+                                            &ctx.svc_exposes(),
+                                            ctx.multi_layer(),
+                                            &ctx.primary_svc_ident().to_string(),
+                                            &ctx.installed_primary_svc_ident()?.to_string(),
+                                            &environment,
+                                            &packages).to_string(),
+        };
+
+        util::write_file(self.build_root.workdir().join("Dockerfile"), &contents)?;
         Ok(())
     }
 
-    /// Build the Docker image locally using the provided naming policy.
+    /// Build the Docker image locally using the provided naming policy and container
+    /// engine.
+    ///
+    /// When building against a remote engine, `persist_volume` reuses a data volume
+    /// keyed by this export's package ident across invocations (see
+    /// `ImageBuilder::persistent_volume`), rather than staging and discarding a scratch
+    /// volume every time.
+    ///
+    /// `registry_auth`, if given, authenticates the implicit push performed by a
+    /// multi-arch (`platforms` non-empty) build; it has no effect otherwise, since a
+    /// single-arch build is pushed later via `DockerImage::push`.
+    #[allow(clippy::too_many_arguments)]
     pub fn export(&self,
                   ui: &mut UI,
                   naming: &Naming,
-                  memory: Option<&str>)
+                  engine: &Engine,
+                  memory: Option<&str>,
+                  build_args: &[(String, String)],
+                  pre_build: &[String],
+                  platforms: &[String],
+                  persist_volume: bool,
+                  registry_auth: Option<(Credentials, Option<String>)>)
                   -> Result<DockerImage> {
         ui.status(Status::Creating, "Docker image")?;
-        let ident = self.0.ctx().installed_primary_svc_ident()?;
-        let channel = self.0.ctx().channel();
+        let ident = self.build_root.ctx().installed_primary_svc_ident()?;
+        let channel = self.build_root.ctx().channel();
 
         // TODO (CM): Ideally, we'd toss this error much earlier,
         // since this error would be based on user input errors
         let (image_name, tags) = naming.image_identifiers(&ident, &channel)?;
 
-        let mut builder = ImageBuilder::new(self.0.workdir(), &image_name);
+        let mut builder = ImageBuilder::new(engine.clone(),
+                                            self.build_root.workdir(),
+                                            &image_name,
+                                            &ident.to_string(),
+                                            &channel);
         for tag in tags {
             builder = builder.tag(tag);
         }
         if let Some(memory) = memory {
             builder = builder.memory(memory);
         }
+        for (key, value) in build_args {
+            builder = builder.build_arg(key, value);
+        }
+        for command in pre_build {
+            builder = builder.pre_build(command);
+        }
+        for platform in platforms {
+            builder = builder.platform(platform);
+        }
+        if persist_volume {
+            builder = builder.persistent_volume(&ident.to_string());
+        }
+        if let Some((credentials, registry_url)) = registry_auth {
+            builder = builder.registry_auth(credentials, registry_url);
+        }
         builder.build()
     }
 }
+
+/// A single image this exporter produced, as reported by the local container engine.
+#[derive(Clone, Debug)]
+pub struct ManagedImage {
+    pub id:      String,
+    pub ident:   String,
+    pub channel: String,
+    pub tag:     String,
+}
+
+/// Enumerates and cleans up artifacts (images and scratch data volumes) that this
+/// exporter has produced, regardless of what export created them.
+///
+/// Every image built by `ImageBuilder` is stamped with the `hab.package.ident` and
+/// `hab.channel` labels, which this type queries to drive `list`/`remove`/`prune`,
+/// mirroring the `list-volumes`/`remove-volumes`/`prune-volumes` utilities in `cross`.
+///
+/// This only reaches artifacts the engine itself knows about (images, named data
+/// volumes). A failed or interrupted *local* export can also leak a `DockerBuildRoot`'s
+/// temporary workdir on disk; cleaning those up is `DockerBuildRoot::destroy`'s job, not
+/// this type's, since `ImageManager` has no engine-queryable way to discover them.
+pub struct ImageManager {
+    engine: Engine,
+}
+
+impl ImageManager {
+    /// Creates a manager operating against the given engine.
+    pub fn new(engine: Engine) -> Self { ImageManager { engine } }
+
+    /// Lists every image this exporter has built.
+    ///
+    /// # Errors
+    ///
+    /// * If querying the engine for labeled images fails
+    pub fn list(&self) -> Result<Vec<ManagedImage>> {
+        let mut cmd = self.engine.command();
+        cmd.arg("images")
+           .arg("--filter")
+           .arg(format!("label={}", IDENT_LABEL))
+           .arg("--format")
+           .arg(format!("{{{{.ID}}}}\t{{{{.Label \"{}\"}}}}\t{{{{.Label \"{}\"}}}}\t\
+                         {{{{.Tag}}}}",
+                        IDENT_LABEL, CHANNEL_LABEL));
+        debug!("Running: {:?}", &cmd);
+        let output = cmd.output()?;
+        if !output.status.success() {
+            return Err(Error::ListImagesFailed(output.status));
+        }
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        Ok(stdout.lines()
+                 .filter_map(|line| {
+                     let mut parts = line.splitn(4, '\t');
+                     Some(ManagedImage { id:      parts.next()?.to_string(),
+                                         ident:   parts.next()?.to_string(),
+                                         channel: parts.next()?.to_string(),
+                                         tag:     parts.next()?.to_string(), })
+                 })
+                 .collect())
+    }
+
+    /// Removes every image whose package ident or channel matches the given filter.
+    /// At least one of `ident` or `channel` must be given.
+    ///
+    /// # Errors
+    ///
+    /// * If neither `ident` nor `channel` is given
+    /// * If listing images fails
+    /// * If removing a matching image fails
+    pub fn remove(&self, ident: Option<&str>, channel: Option<&str>) -> Result<()> {
+        if ident.is_none() && channel.is_none() {
+            return Err(Error::RemoveFilterRequired);
+        }
+        for image in self.list()?.into_iter().filter(|image| {
+                                                  ident.map_or(true, |i| image.ident == i)
+                                                  && channel.map_or(true, |c| image.channel == c)
+                                              })
+        {
+            let mut cmd = self.engine.command();
+            cmd.arg("rmi").arg(&image.id);
+            debug!("Running: {:?}", &cmd);
+            let exit_status = cmd.spawn()?.wait()?;
+            if !exit_status.success() {
+                return Err(Error::RemoveImageFailed(exit_status).into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Removes scratch data volumes left behind by failed or interrupted remote
+    /// exports.
+    ///
+    /// Volumes named `hab-export-scratch-*` are always transient leftovers; persistent
+    /// volumes (named `hab-export-<ident>`, reused across exports) are left alone.
+    ///
+    /// Scoped to data volumes only: a leaked `DockerBuildRoot` temporary workdir from a
+    /// failed *local* export is a plain directory on disk, not something `docker volume
+    /// ls` can see, so it isn't pruned here. That cleanup belongs to
+    /// `DockerBuildRoot::destroy`.
+    ///
+    /// # Errors
+    ///
+    /// * If listing or removing volumes fails
+    pub fn prune_volumes(&self) -> Result<()> {
+        let mut cmd = self.engine.command();
+        cmd.arg("volume")
+           .arg("ls")
+           .arg("--filter")
+           .arg("name=hab-export-scratch-")
+           .arg("--format")
+           .arg("{{.Name}}");
+        debug!("Running: {:?}", &cmd);
+        let output = cmd.output()?;
+        if !output.status.success() {
+            return Err(Error::ListVolumesFailed(output.status));
+        }
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        for name in stdout.lines() {
+            let mut cmd = self.engine.command();
+            cmd.arg("volume").arg("rm").arg("-f").arg(name);
+            debug!("Running: {:?}", &cmd);
+            let exit_status = cmd.spawn()?.wait()?;
+            if !exit_status.success() {
+                return Err(Error::RemoveVolumeFailed(name.to_string(), exit_status));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn environment() -> Vec<(String, String)> {
+        vec![("HAB_LICENSE".to_string(), "accept-no-persist".to_string())]
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn dockerfile_instructions_single_layer() {
+        let dockerfile = dockerfile_instructions("core/centos7",
+                                                  "rootfs",
+                                                  "/bin:/usr/bin",
+                                                  "/hab/pkgs/core/hab/0.1.0/1/bin/hab",
+                                                  &["8080".to_string()],
+                                                  false,
+                                                  "core/redis",
+                                                  "core/redis/4.0.14/20190319155852",
+                                                  &environment(),
+                                                  &[]);
+        let rendered = dockerfile.to_string();
+        let lines: Vec<&str> = rendered.lines().collect();
+
+        assert_eq!(lines[0], "FROM core/centos7");
+        assert_eq!(lines[1], "COPY rootfs /");
+        assert_eq!(lines[2], "ENV PATH=/bin:/usr/bin");
+        assert_eq!(lines[3], "ENV HAB_LICENSE=accept-no-persist");
+        assert_eq!(lines[4], "LABEL HABITAT_PRIMARY_SERVICE=\"core/redis\"");
+        assert_eq!(lines[5],
+                  "LABEL HABITAT_INSTALLED_PRIMARY_SERVICE=\"core/redis/4.0.14/20190319155852\"");
+        assert_eq!(lines[6], "EXPOSE 8080");
+        assert_eq!(lines[7], "ENTRYPOINT [\"/init.sh\"]");
+        assert_eq!(lines[8],
+                  "CMD [\"/hab/pkgs/core/hab/0.1.0/1/bin/hab\", \"sup\", \"run\"]");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn dockerfile_instructions_multi_layer_copies_each_package() {
+        let packages = vec!["core/glibc/2.29/20190319155852".to_string(),
+                            "core/redis/4.0.14/20190319155852".to_string()];
+        let dockerfile = dockerfile_instructions("core/centos7",
+                                                  "rootfs",
+                                                  "/bin:/usr/bin",
+                                                  "/hab/pkgs/core/hab/0.1.0/1/bin/hab",
+                                                  &[],
+                                                  true,
+                                                  "core/redis",
+                                                  "core/redis/4.0.14/20190319155852",
+                                                  &environment(),
+                                                  &packages);
+        let rendered = dockerfile.to_string();
+        let lines: Vec<&str> = rendered.lines().collect();
+
+        assert_eq!(lines[0], "FROM core/centos7");
+        assert_eq!(lines[1],
+                  "COPY rootfs/hab/pkgs/core/glibc/2.29/20190319155852 \
+                   /hab/pkgs/core/glibc/2.29/20190319155852");
+        assert_eq!(lines[2],
+                  "COPY rootfs/hab/pkgs/core/redis/4.0.14/20190319155852 \
+                   /hab/pkgs/core/redis/4.0.14/20190319155852");
+        // No EXPOSE instruction should be emitted when there are no exposed ports.
+        assert!(!rendered.contains("EXPOSE"));
+    }
+
+    #[test]
+    fn custom_dockerfile_context_preserves_chunk0_3_shape() {
+        let environment = json!({ "HAB_LICENSE": "accept-no-persist" });
+        let context = custom_dockerfile_context("core/centos7",
+                                                 "rootfs",
+                                                 "/bin:/usr/bin",
+                                                 "/hab/pkgs/core/hab/0.1.0/1/bin/hab",
+                                                 &["8080".to_string(), "8443".to_string()],
+                                                 false,
+                                                 "core/redis",
+                                                 "core/redis/4.0.14/20190319155852",
+                                                 environment,
+                                                 &[]);
+
+        // `exposes` is a single space-joined string, e.g. for `EXPOSE {{exposes}}`.
+        assert_eq!(context["exposes"], "8080 8443");
+        // `environment` stays a `{key: value}` object, e.g. for
+        // `{{#each environment}}{{@key}}={{this}}{{/each}}`.
+        assert_eq!(context["environment"]["HAB_LICENSE"], "accept-no-persist");
+    }
+
+    #[test]
+    fn quoted_argv_quotes_each_element() {
+        assert_eq!(quoted_argv(&["/init.sh".to_string()]), "\"/init.sh\"");
+        assert_eq!(quoted_argv(&["hab".to_string(), "sup".to_string(), "run".to_string()]),
+                  "\"hab\", \"sup\", \"run\"");
+    }
+}
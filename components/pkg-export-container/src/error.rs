@@ -0,0 +1,112 @@
+use std::{error,
+          fmt,
+          io,
+          process::ExitStatus,
+          result};
+
+use crate::docker::EngineKind;
+
+pub type Result<T> = result::Result<T, Error>;
+
+/// Errors that can occur while building, pushing, or managing container images.
+#[derive(Debug)]
+pub enum Error {
+    /// A `docker build` (or `buildx build`) invocation exited non-zero.
+    BuildFailed(ExitStatus),
+    /// No local image could be found matching a built image's tag.
+    DockerImageIdNotFound(String),
+    /// A `docker push` invocation exited non-zero.
+    PushImageFailed(ExitStatus),
+    /// A `docker rmi` invocation exited non-zero.
+    RemoveImageFailed(ExitStatus),
+    /// An unrecognized container engine name was requested.
+    UnknownEngine(String),
+    /// A container engine's binary could not be found on `PATH`.
+    EngineBinaryNotFound(String),
+    /// A data volume could not be created on the engine's host.
+    CreateVolumeFailed(String, ExitStatus),
+    /// A data volume could not be populated from a local workdir.
+    PopulateVolumeFailed(String, ExitStatus),
+    /// A data volume could not be removed from the engine's host.
+    RemoveVolumeFailed(String, ExitStatus),
+    /// Listing data volumes on the engine's host failed.
+    ListVolumesFailed(ExitStatus),
+    /// A configured pre-build command exited non-zero.
+    PreBuildCommandFailed(String, ExitStatus),
+    /// A multi-architecture (`buildx`) build was requested against an engine that
+    /// doesn't support it.
+    MultiArchUnsupportedEngine(EngineKind),
+    /// Listing built images failed.
+    ListImagesFailed(ExitStatus),
+    /// `ImageManager::remove` was called without an `ident` or `channel` filter.
+    RemoveFilterRequired,
+    /// An I/O error occurred.
+    Io(io::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::BuildFailed(status) => {
+                write!(f, "docker build failed (exit status: {})", status)
+            }
+            Error::DockerImageIdNotFound(tag) => {
+                write!(f, "could not find image ID for tag '{}'", tag)
+            }
+            Error::PushImageFailed(status) => {
+                write!(f, "docker push failed (exit status: {})", status)
+            }
+            Error::RemoveImageFailed(status) => {
+                write!(f, "docker rmi failed (exit status: {})", status)
+            }
+            Error::UnknownEngine(name) => write!(f, "unknown container engine '{}'", name),
+            Error::EngineBinaryNotFound(binary) => {
+                write!(f, "'{}' binary not found on PATH", binary)
+            }
+            Error::CreateVolumeFailed(name, status) => {
+                write!(f,
+                      "failed to create data volume '{}' (exit status: {})",
+                      name, status)
+            }
+            Error::PopulateVolumeFailed(name, status) => {
+                write!(f,
+                      "failed to populate data volume '{}' (exit status: {})",
+                      name, status)
+            }
+            Error::RemoveVolumeFailed(name, status) => {
+                write!(f,
+                      "failed to remove data volume '{}' (exit status: {})",
+                      name, status)
+            }
+            Error::ListVolumesFailed(status) => {
+                write!(f, "failed to list data volumes (exit status: {})", status)
+            }
+            Error::PreBuildCommandFailed(command, status) => {
+                write!(f,
+                      "pre-build command failed (exit status: {}): {}",
+                      status, command)
+            }
+            Error::MultiArchUnsupportedEngine(kind) => {
+                write!(f,
+                      "multi-architecture builds via `buildx` are only supported for \
+                       the Docker engine, not '{}'",
+                      kind)
+            }
+            Error::ListImagesFailed(status) => {
+                write!(f, "failed to list images (exit status: {})", status)
+            }
+            Error::RemoveFilterRequired => {
+                write!(f,
+                      "at least one of `ident` or `channel` must be given to \
+                       `ImageManager::remove`")
+            }
+            Error::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self { Error::Io(err) }
+}